@@ -1,13 +1,18 @@
+use base64::Engine;
+use ext_php_rs::binary::Binary;
 use ext_php_rs::prelude::*;
+use ext_php_rs::types::{ZendHashTable, Zval};
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
 use std::time::Duration;
 
 #[php_class]
 #[derive(Debug, Clone)]
 pub struct HttpResponse {
     pub status: i32,
-    pub headers: HashMap<String, String>,
-    pub body: String,
+    pub headers: HashMap<String, Vec<String>>,
+    pub body: Vec<u8>,
 }
 
 #[php_impl]
@@ -16,7 +21,7 @@ impl HttpResponse {
         Self {
             status: 200,
             headers: HashMap::new(),
-            body: String::new(),
+            body: Vec::new(),
         }
     }
 
@@ -25,19 +30,44 @@ impl HttpResponse {
         self.status
     }
 
-    /// Get response headers
+    /// Get the first value of a response header
     pub fn headers(&self, name: String) -> Option<String> {
-        self.headers.get(&name.to_lowercase()).cloned()
+        self.headers.get(&name.to_lowercase())?.first().cloned()
     }
 
-    /// Get response body
-    pub fn body(&self) -> String {
-        self.body.clone()
+    /// Get all values of a response header, for repeatable headers like `Set-Cookie`
+    pub fn headers_all(&self, name: String) -> Vec<String> {
+        self.headers.get(&name.to_lowercase()).cloned().unwrap_or_default()
     }
 
-    /// Parse JSON from response body
-    pub fn json(&self) -> PhpResult<HashMap<String, String>> {
-        match serde_json::from_str::<serde_json::Value>(&self.body) {
+    /// Get response body, decoded as UTF-8
+    pub fn body(&self) -> PhpResult<String> {
+        String::from_utf8(self.body.clone())
+            .map_err(|e| PhpException::default(format!("Response body is not valid UTF-8: {}", e)).into())
+    }
+
+    /// Get the raw response body bytes as a PHP binary string, for binary
+    /// payloads (images, gzip, protobuf, PDFs) that aren't valid UTF-8
+    pub fn body_bytes(&self) -> Binary<u8> {
+        Binary::from(self.body.clone())
+    }
+
+    /// Parse the response body as JSON, preserving the native shape: nested
+    /// objects and arrays become PHP arrays, and numbers/bools/null keep
+    /// their PHP types instead of being stringified.
+    pub fn json(&self) -> PhpResult<Zval> {
+        let body = self.body()?;
+        let value = serde_json::from_str::<serde_json::Value>(&body)
+            .map_err(|e| PhpException::default(format!("JSON parse error: {}", e)))?;
+        json_value_to_zval(&value)
+    }
+
+    /// Parse JSON from the response body, flattening every value to its
+    /// string representation. Kept for callers relying on the old shape;
+    /// prefer `json()` for nested objects, arrays, and typed values.
+    pub fn json_flat(&self) -> PhpResult<HashMap<String, String>> {
+        let body = self.body()?;
+        match serde_json::from_str::<serde_json::Value>(&body) {
             Ok(value) => {
                 let mut result = HashMap::new();
                 if let serde_json::Value::Object(map) = value {
@@ -52,8 +82,427 @@ impl HttpResponse {
     }
 }
 
+/// Convert a parsed `serde_json::Value` into the matching native PHP value:
+/// objects and arrays become PHP arrays, numbers/bools/null/strings keep
+/// their PHP type.
+fn json_value_to_zval(value: &serde_json::Value) -> PhpResult<Zval> {
+    let mut zval = Zval::new();
+
+    match value {
+        serde_json::Value::Null => zval.set_null(),
+        serde_json::Value::Bool(b) => zval.set_bool(*b),
+        serde_json::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                zval.set_long(i);
+            } else if let Some(f) = n.as_f64() {
+                zval.set_double(f);
+            }
+        }
+        serde_json::Value::String(s) => zval
+            .set_string(s, false)
+            .map_err(|e| PhpException::default(format!("Could not build string value: {:?}", e)))?,
+        serde_json::Value::Array(items) => {
+            let mut ht = ZendHashTable::new();
+            for item in items {
+                ht.push(json_value_to_zval(item)?)
+                    .map_err(|e| PhpException::default(format!("Could not build array value: {:?}", e)))?;
+            }
+            zval.set_hashtable(ht);
+        }
+        serde_json::Value::Object(map) => {
+            let mut ht = ZendHashTable::new();
+            for (k, v) in map {
+                ht.insert(k, json_value_to_zval(v)?)
+                    .map_err(|e| PhpException::default(format!("Could not build object value: {:?}", e)))?;
+            }
+            zval.set_hashtable(ht);
+        }
+    }
+
+    Ok(zval)
+}
+
+/// Convert a PHP value (scalar, list array, or associative array) into the
+/// matching `serde_json::Value`, the inverse of `json_value_to_zval`.
+fn zval_to_json_value(zval: &Zval) -> PhpResult<serde_json::Value> {
+    if zval.is_null() {
+        Ok(serde_json::Value::Null)
+    } else if let Some(b) = zval.bool() {
+        Ok(serde_json::Value::Bool(b))
+    } else if let Some(i) = zval.long() {
+        Ok(serde_json::Value::Number(i.into()))
+    } else if let Some(f) = zval.double() {
+        Ok(serde_json::Value::Number(
+            serde_json::Number::from_f64(f).unwrap_or_else(|| 0.into()),
+        ))
+    } else if let Some(s) = zval.string() {
+        Ok(serde_json::Value::String(s.to_string()))
+    } else if let Some(ht) = zval.array() {
+        if ht.has_sequential_keys() {
+            let mut items = Vec::with_capacity(ht.len());
+            for (_, _, value) in ht.iter() {
+                items.push(zval_to_json_value(value)?);
+            }
+            Ok(serde_json::Value::Array(items))
+        } else {
+            let mut map = serde_json::Map::new();
+            for (index, key, value) in ht.iter() {
+                let key = key.unwrap_or_else(|| index.to_string());
+                map.insert(key, zval_to_json_value(value)?);
+            }
+            Ok(serde_json::Value::Object(map))
+        }
+    } else {
+        Err(PhpException::default("Unsupported value in JSON body".to_string()).into())
+    }
+}
+
+/// Serialize a PHP array to a JSON string, for use as a `request()` body
+/// alongside a `Content-Type: application/json` header
+#[php_function]
+pub fn json_body(params: &Zval) -> PhpResult<String> {
+    let value = zval_to_json_value(params)?;
+    serde_json::to_string(&value).map_err(|e| PhpException::default(format!("JSON encode error: {}", e)).into())
+}
+
+/// A persistent HTTP client with connection reuse, a base URL, and default headers.
+///
+/// Unlike the free `request()` function, which builds a one-shot `ureq`
+/// request and throws the agent away, `HttpClient` keeps a single
+/// `ureq::Agent` alive for the lifetime of the PHP object so TLS connections
+/// and keep-alive sockets are reused across calls.
+#[php_class]
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    agent: ureq::Agent,
+    base_url: String,
+    default_headers: HashMap<String, String>,
+    default_timeout: Option<i32>,
+    max_retries: u32,
+    base_delay_ms: u64,
+    max_delay_ms: u64,
+}
+
+#[php_impl]
+impl HttpClient {
+    pub fn __construct(
+        base_url: String,
+        headers: Option<HashMap<String, String>>,
+        timeout: Option<i32>,
+        user_agent: Option<String>,
+        max_redirects: Option<u32>,
+        connect_timeout: Option<i32>,
+        max_retries: Option<u32>,
+        base_delay_ms: Option<u64>,
+        max_delay_ms: Option<u64>,
+    ) -> Self {
+        let mut builder = ureq::AgentBuilder::new();
+
+        if let Some(timeout_secs) = timeout {
+            builder = builder.timeout(Duration::from_secs(timeout_secs as u64));
+        }
+
+        if let Some(connect_timeout_secs) = connect_timeout {
+            builder = builder.timeout_connect(Duration::from_secs(connect_timeout_secs as u64));
+        }
+
+        if let Some(redirects) = max_redirects {
+            builder = builder.redirects(redirects);
+        }
+
+        if let Some(ua) = &user_agent {
+            builder = builder.user_agent(ua);
+        }
+
+        let mut default_headers = headers.unwrap_or_default();
+        if let Some(ua) = user_agent {
+            default_headers.entry("User-Agent".to_string()).or_insert(ua);
+        }
+
+        Self {
+            agent: builder.build(),
+            base_url,
+            default_headers,
+            default_timeout: timeout,
+            max_retries: max_retries.unwrap_or(0),
+            base_delay_ms: base_delay_ms.unwrap_or(DEFAULT_BASE_DELAY_MS),
+            max_delay_ms: max_delay_ms.unwrap_or(DEFAULT_MAX_DELAY_MS),
+        }
+    }
+
+    /// HTTP GET request
+    pub fn get(&self, path: String, headers: Option<HashMap<String, String>>) -> PhpResult<HttpResponse> {
+        self.call("GET", path, headers, None)
+    }
+
+    /// HTTP POST request
+    pub fn post(
+        &self,
+        path: String,
+        body: Option<String>,
+        headers: Option<HashMap<String, String>>,
+    ) -> PhpResult<HttpResponse> {
+        self.call("POST", path, headers, body)
+    }
+
+    /// HTTP PUT request
+    pub fn put(
+        &self,
+        path: String,
+        body: Option<String>,
+        headers: Option<HashMap<String, String>>,
+    ) -> PhpResult<HttpResponse> {
+        self.call("PUT", path, headers, body)
+    }
+
+    /// HTTP PATCH request
+    pub fn patch(
+        &self,
+        path: String,
+        body: Option<String>,
+        headers: Option<HashMap<String, String>>,
+    ) -> PhpResult<HttpResponse> {
+        self.call("PATCH", path, headers, body)
+    }
+
+    /// HTTP DELETE request
+    pub fn delete(&self, path: String, headers: Option<HashMap<String, String>>) -> PhpResult<HttpResponse> {
+        self.call("DELETE", path, headers, None)
+    }
+
+    /// Return a copy of this client with a default `Authorization: Basic` header
+    pub fn with_basic_auth(&self, username: String, password: String) -> PhpResult<Self> {
+        let mut client = self.clone();
+        client.default_headers.retain(|k, _| !k.eq_ignore_ascii_case("authorization"));
+        client
+            .default_headers
+            .insert("Authorization".to_string(), basic_auth(username, password)?);
+        Ok(client)
+    }
+
+    /// Return a copy of this client with a default `Authorization: Bearer` header
+    pub fn with_bearer_token(&self, token: String) -> Self {
+        let mut client = self.clone();
+        client.default_headers.retain(|k, _| !k.eq_ignore_ascii_case("authorization"));
+        client
+            .default_headers
+            .insert("Authorization".to_string(), bearer_token(token));
+        client
+    }
+}
+
+impl HttpClient {
+    /// Merge per-call headers over the client defaults (case-insensitive on
+    /// header names) and resolve `path` against `base_url`, then dispatch the
+    /// request on the shared agent.
+    fn call(
+        &self,
+        method: &str,
+        path: String,
+        headers: Option<HashMap<String, String>>,
+        body: Option<String>,
+    ) -> PhpResult<HttpResponse> {
+        let url = self.resolve_url(&path);
+
+        let mut merged: HashMap<String, String> = self
+            .default_headers
+            .iter()
+            .map(|(k, v)| (k.to_lowercase(), v.clone()))
+            .collect();
+
+        if let Some(call_headers) = headers {
+            for (key, value) in call_headers {
+                merged.insert(key.to_lowercase(), value);
+            }
+        }
+
+        // Match the free `post`/`put`/`patch` functions: default to JSON when
+        // there's a body and the caller hasn't set a Content-Type.
+        if body.is_some() && !merged.contains_key("content-type") {
+            merged.insert("content-type".to_string(), "application/json".to_string());
+        }
+
+        request_with_retry(
+            &self.agent,
+            method.to_string(),
+            url,
+            Some(merged),
+            body,
+            self.default_timeout,
+            self.max_retries,
+            self.base_delay_ms,
+            self.max_delay_ms,
+        )
+    }
+
+    fn resolve_url(&self, path: &str) -> String {
+        match url::Url::parse(&self.base_url) {
+            Ok(base) => base.join(path).map(|u| u.to_string()).unwrap_or_else(|_| path.to_string()),
+            Err(_) => format!("{}{}", self.base_url, path),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum MultipartPart {
+    Field { name: String, value: String },
+    File { name: String, path: String, filename: String, content_type: String },
+}
+
+/// A `multipart/form-data` body builder, for uploading files alongside
+/// regular fields. Build one up with `add_field`/`add_file`, then hand it to
+/// `post_multipart()`.
+#[php_class]
+#[derive(Debug, Clone, Default)]
+pub struct MultipartForm {
+    parts: Vec<MultipartPart>,
+}
+
+#[php_impl]
+impl MultipartForm {
+    pub fn __construct() -> Self {
+        Self::default()
+    }
+
+    /// Add a plain text field
+    pub fn add_field(&mut self, name: String, value: String) {
+        self.parts.push(MultipartPart::Field { name, value });
+    }
+
+    /// Add a file, read from disk at send time
+    pub fn add_file(
+        &mut self,
+        name: String,
+        path: String,
+        filename: Option<String>,
+        content_type: Option<String>,
+    ) {
+        let filename = filename.unwrap_or_else(|| {
+            std::path::Path::new(&path)
+                .file_name()
+                .and_then(|f| f.to_str())
+                .unwrap_or("file")
+                .to_string()
+        });
+
+        self.parts.push(MultipartPart::File {
+            name,
+            path,
+            filename,
+            content_type: content_type.unwrap_or_else(|| "application/octet-stream".to_string()),
+        });
+    }
+}
+
+impl MultipartForm {
+    /// Serialize the parts into a `multipart/form-data` payload, returning
+    /// the body bytes and the boundary used to build them.
+    fn encode(&self) -> PhpResult<(Vec<u8>, String)> {
+        // Render each part's header and content up front so we can pick a
+        // boundary that's guaranteed not to collide with any of them.
+        let mut rendered: Vec<(Vec<u8>, Vec<u8>)> = Vec::with_capacity(self.parts.len());
+
+        for part in &self.parts {
+            match part {
+                MultipartPart::Field { name, value } => {
+                    let header = format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", name);
+                    rendered.push((header.into_bytes(), value.clone().into_bytes()));
+                }
+                MultipartPart::File { name, path, filename, content_type } => {
+                    let mut file = File::open(path)
+                        .map_err(|e| PhpException::default(format!("Could not open file {}: {}", path, e)))?;
+                    let mut contents = Vec::new();
+                    file.read_to_end(&mut contents)
+                        .map_err(|e| PhpException::default(format!("Could not read file {}: {}", path, e)))?;
+
+                    let header = format!(
+                        "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                        name, filename, content_type
+                    );
+                    rendered.push((header.into_bytes(), contents));
+                }
+            }
+        }
+
+        let boundary = random_boundary(&rendered);
+
+        let mut body = Vec::new();
+        for (header, content) in &rendered {
+            body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+            body.extend_from_slice(header);
+            body.extend_from_slice(content);
+            body.extend_from_slice(b"\r\n");
+        }
+
+        body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+        Ok((body, boundary))
+    }
+}
+
+/// Generate a high-entropy boundary that doesn't occur inside any rendered
+/// part, retrying with fresh randomness on the (astronomically unlikely)
+/// chance of a collision.
+fn random_boundary(rendered: &[(Vec<u8>, Vec<u8>)]) -> String {
+    for _ in 0..10 {
+        let boundary = format!("ElephantBoundary{:016x}{:016x}", random_u64(), random_u64());
+        let boundary_bytes = boundary.as_bytes();
+
+        let collides = rendered.iter().any(|(header, content)| {
+            contains_subslice(header, boundary_bytes) || contains_subslice(content, boundary_bytes)
+        });
+
+        if !collides {
+            return boundary;
+        }
+    }
+
+    // Practically unreachable with 128 bits of entropy per attempt, but
+    // never loop forever.
+    format!("ElephantBoundary{:016x}{:016x}", random_u64(), random_u64())
+}
+
+fn contains_subslice(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+/// A pseudo-random `u64` seeded from the OS, without pulling in a `rand` dependency.
+fn random_u64() -> u64 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    RandomState::new().build_hasher().finish()
+}
+
+/// Upload a `multipart/form-data` body built with `MultipartForm`
+#[php_function]
+pub fn post_multipart(
+    url: String,
+    form: &MultipartForm,
+    headers: Option<HashMap<String, String>>,
+) -> PhpResult<HttpResponse> {
+    let (body, boundary) = form.encode()?;
+
+    let mut req = ureq::post(&url);
+
+    if let Some(headers_map) = headers {
+        for (key, value) in headers_map {
+            req = req.set(&key, &value);
+        }
+    }
+
+    req = req.set("Content-Type", &format!("multipart/form-data; boundary={}", boundary));
+
+    req.send_bytes(&body)
+        .map_err(|e| PhpException::default(format!("HTTP request failed: {}", e)).into())
+        .and_then(build_response)
+}
+
 // HTTP functions in namespace Elephant\Net\Http
 
+const DEFAULT_BASE_DELAY_MS: u64 = 200;
+const DEFAULT_MAX_DELAY_MS: u64 = 5_000;
+
 #[php_function]
 pub fn request(
     method: String,
@@ -61,14 +510,122 @@ pub fn request(
     headers: Option<HashMap<String, String>>,
     body: Option<String>,
     timeout: Option<i32>,
+    max_redirects: Option<u32>,
+    connect_timeout: Option<i32>,
+    max_retries: Option<u32>,
+    base_delay_ms: Option<u64>,
+    max_delay_ms: Option<u64>,
 ) -> PhpResult<HttpResponse> {
+    let mut builder = ureq::AgentBuilder::new();
+
+    if let Some(redirects) = max_redirects {
+        builder = builder.redirects(redirects);
+    }
+
+    if let Some(connect_timeout_secs) = connect_timeout {
+        builder = builder.timeout_connect(Duration::from_secs(connect_timeout_secs as u64));
+    }
+
+    request_with_retry(
+        &builder.build(),
+        method,
+        url,
+        headers,
+        body,
+        timeout,
+        max_retries.unwrap_or(0),
+        base_delay_ms.unwrap_or(DEFAULT_BASE_DELAY_MS),
+        max_delay_ms.unwrap_or(DEFAULT_MAX_DELAY_MS),
+    )
+}
+
+/// Run a request on `agent`, retrying on a connection error or a 5xx/429
+/// response with exponential backoff (`base_delay * 2^attempt`, capped at
+/// `max_delay_ms`, plus up to a quarter of that as jitter), honoring a numeric
+/// `Retry-After` header (up to `max_delay_ms`) when the upstream sends one.
+/// 4xx responses other than 429 are never retried.
+#[allow(clippy::too_many_arguments)]
+fn request_with_retry(
+    agent: &ureq::Agent,
+    method: String,
+    url: String,
+    headers: Option<HashMap<String, String>>,
+    body: Option<String>,
+    timeout: Option<i32>,
+    max_retries: u32,
+    base_delay_ms: u64,
+    max_delay_ms: u64,
+) -> PhpResult<HttpResponse> {
+    let mut attempt = 0;
+
+    loop {
+        match dispatch(agent, &method, &url, &headers, &body, timeout)? {
+            Ok(resp) => return build_response(resp),
+            Err(ureq::Error::Status(code, resp)) => {
+                let retryable = attempt < max_retries && (code >= 500 || code == 429);
+                if !retryable {
+                    return Err(PhpException::default(format!(
+                        "HTTP request failed: {} {}",
+                        code,
+                        resp.status_text()
+                    ))
+                    .into());
+                }
+
+                let retry_after = resp.header("Retry-After").and_then(|value| value.parse::<u64>().ok());
+                std::thread::sleep(backoff_delay(attempt, base_delay_ms, max_delay_ms, retry_after));
+                attempt += 1;
+            }
+            Err(e @ ureq::Error::Transport(_)) => {
+                if attempt >= max_retries {
+                    return Err(PhpException::default(format!("HTTP request failed: {}", e)).into());
+                }
+
+                std::thread::sleep(backoff_delay(attempt, base_delay_ms, max_delay_ms, None));
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Compute the delay before the next retry attempt: an explicit `Retry-After`
+/// value replaces the backoff (clamped to `max_delay_ms`, no jitter),
+/// otherwise exponential backoff capped at `max_delay_ms`, plus up to a
+/// quarter of that capped value as jitter to avoid a thundering herd.
+fn backoff_delay(attempt: u32, base_delay_ms: u64, max_delay_ms: u64, retry_after_secs: Option<u64>) -> Duration {
+    if let Some(secs) = retry_after_secs {
+        return Duration::from_secs(secs).min(Duration::from_millis(max_delay_ms));
+    }
+
+    let backoff_ms = base_delay_ms.saturating_mul(1u64 << attempt.min(32)).min(max_delay_ms);
+
+    let jitter_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64 % (backoff_ms / 4 + 1))
+        .unwrap_or(0);
+
+    Duration::from_millis(backoff_ms + jitter_ms)
+}
+
+/// Build the request for `method`/`url` on `agent` and send it, returning the
+/// raw `ureq` result so callers can distinguish a 5xx/429 `Error::Status`
+/// (retryable, carries the response for `Retry-After`) from a 4xx one (not
+/// retryable) and from a transport-level connection error.
+fn dispatch(
+    agent: &ureq::Agent,
+    method: &str,
+    url: &str,
+    headers: &Option<HashMap<String, String>>,
+    body: &Option<String>,
+    timeout: Option<i32>,
+) -> PhpResult<Result<ureq::Response, ureq::Error>> {
     let mut req = match method.to_uppercase().as_str() {
-        "GET" => ureq::get(&url),
-        "POST" => ureq::post(&url),
-        "PUT" => ureq::put(&url),
-        "DELETE" => ureq::delete(&url),
-        "PATCH" => ureq::patch(&url),
-        "HEAD" => ureq::head(&url),
+        "GET" => agent.get(url),
+        "POST" => agent.post(url),
+        "PUT" => agent.put(url),
+        "DELETE" => agent.delete(url),
+        "PATCH" => agent.patch(url),
+        "HEAD" => agent.head(url),
         _ => return Err(PhpException::default(format!("Unsupported method: {}", method)).into()),
     };
 
@@ -78,44 +635,54 @@ pub fn request(
 
     if let Some(headers_map) = headers {
         for (key, value) in headers_map {
-            req = req.set(&key, &value);
+            req = req.set(key, value);
         }
     }
 
-    let response = if let Some(body_data) = body {
-        req.send_string(&body_data)
+    Ok(if let Some(body_data) = body {
+        req.send_string(body_data)
     } else {
         req.call()
-    };
+    })
+}
 
-    match response {
-        Ok(resp) => {
-            let status = resp.status() as i32;
+/// Convert a `ureq::Response` into our `HttpResponse`, collecting every
+/// value for each header name and reading the body to raw bytes.
+fn build_response(resp: ureq::Response) -> PhpResult<HttpResponse> {
+    let status = resp.status() as i32;
+
+    // `headers_names()` yields one entry per header line, so repeated
+    // headers (e.g. multiple `Set-Cookie`) show up more than once; dedupe
+    // the names before asking `all()` for every value of each, or those
+    // values get collected multiple times over.
+    let mut seen_names: std::collections::HashSet<String> = std::collections::HashSet::new();
+    let mut headers: HashMap<String, Vec<String>> = HashMap::new();
+    for header_name in resp.headers_names() {
+        let name = header_name.to_lowercase();
+        if !seen_names.insert(name.clone()) {
+            continue;
+        }
 
-            let mut headers = HashMap::new();
-            for header_name in resp.headers_names() {
-                if let Some(header_value) = resp.header(&header_name) {
-                    headers.insert(header_name.to_lowercase(), header_value.to_string());
-                }
-            }
+        let values: Vec<String> = resp.all(&header_name).iter().map(|value| value.to_string()).collect();
+        headers.insert(name, values);
+    }
 
-            let body = resp.into_string()
-                .map_err(|e| PhpException::default(format!("Response body error: {}", e)))?;
+    let mut body = Vec::new();
+    resp.into_reader()
+        .read_to_end(&mut body)
+        .map_err(|e| PhpException::default(format!("Response body error: {}", e)))?;
 
-            Ok(HttpResponse {
-                status,
-                headers,
-                body,
-            })
-        }
-        Err(e) => Err(PhpException::default(format!("HTTP request failed: {}", e)).into()),
-    }
+    Ok(HttpResponse {
+        status,
+        headers,
+        body,
+    })
 }
 
 /// HTTP GET request
 #[php_function]
 pub fn get(url: String, headers: Option<HashMap<String, String>>) -> PhpResult<HttpResponse> {
-    request("GET".to_string(), url, headers, None, Some(30))
+    request("GET".to_string(), url, headers, None, Some(30), None, None, None, None, None)
 }
 
 /// HTTP POST request
@@ -132,7 +699,7 @@ pub fn post(
         final_headers.insert("Content-Type".to_string(), "application/json".to_string());
     }
 
-    request("POST".to_string(), url, Some(final_headers), body, Some(30))
+    request("POST".to_string(), url, Some(final_headers), body, Some(30), None, None, None, None, None)
 }
 
 /// HTTP PUT request
@@ -148,13 +715,13 @@ pub fn put(
         final_headers.insert("Content-Type".to_string(), "application/json".to_string());
     }
 
-    request("PUT".to_string(), url, Some(final_headers), body, Some(30))
+    request("PUT".to_string(), url, Some(final_headers), body, Some(30), None, None, None, None, None)
 }
 
 /// HTTP DELETE request
 #[php_function]
 pub fn delete(url: String, headers: Option<HashMap<String, String>>) -> PhpResult<HttpResponse> {
-    request("DELETE".to_string(), url, headers, None, Some(30))
+    request("DELETE".to_string(), url, headers, None, Some(30), None, None, None, None, None)
 }
 
 /// HTTP PATCH request
@@ -170,19 +737,67 @@ pub fn patch(
         final_headers.insert("Content-Type".to_string(), "application/json".to_string());
     }
 
-    request("PATCH".to_string(), url, Some(final_headers), body, Some(30))
+    request("PATCH".to_string(), url, Some(final_headers), body, Some(30), None, None, None, None, None)
 }
 
 /// HTTP HEAD request
 #[php_function]
 pub fn head(url: String, headers: Option<HashMap<String, String>>) -> PhpResult<HttpResponse> {
-    request("HEAD".to_string(), url, headers, None, Some(30))
+    request("HEAD".to_string(), url, headers, None, Some(30), None, None, None, None, None)
 }
 
 /// HTTP OPTIONS request
 #[php_function]
 pub fn options(url: String, headers: Option<HashMap<String, String>>) -> PhpResult<HttpResponse> {
-    request("OPTIONS".to_string(), url, headers, None, Some(30))
+    request("OPTIONS".to_string(), url, headers, None, Some(30), None, None, None, None, None)
+}
+
+/// Build an `Authorization: Basic` header value from a username and password
+#[php_function]
+pub fn basic_auth(username: String, password: String) -> PhpResult<String> {
+    if username.contains(':') {
+        return Err(PhpException::default("Username must not contain a colon".to_string()).into());
+    }
+
+    let encoded = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+    Ok(format!("Basic {}", encoded))
+}
+
+/// Build an `Authorization: Bearer` header value from a token
+#[php_function]
+pub fn bearer_token(token: String) -> String {
+    format!("Bearer {}", token)
+}
+
+/// Stream a GET response directly to a file on disk, without buffering the
+/// whole body in memory. Returns the response status once the download
+/// completes.
+#[php_function]
+pub fn download(url: String, dest_path: String, headers: Option<HashMap<String, String>>) -> PhpResult<i32> {
+    let mut req = ureq::get(&url);
+
+    if let Some(headers_map) = headers {
+        for (key, value) in headers_map {
+            req = req.set(&key, &value);
+        }
+    }
+
+    let resp = req
+        .call()
+        .map_err(|e| PhpException::default(format!("HTTP request failed: {}", e)))?;
+
+    let status = resp.status() as i32;
+
+    let mut file = File::create(&dest_path)
+        .map_err(|e| PhpException::default(format!("Could not create file {}: {}", dest_path, e)))?;
+
+    std::io::copy(&mut resp.into_reader(), &mut file)
+        .map_err(|e| PhpException::default(format!("Download failed: {}", e)))?;
+
+    file.flush()
+        .map_err(|e| PhpException::default(format!("Could not flush file {}: {}", dest_path, e)))?;
+
+    Ok(status)
 }
 
 